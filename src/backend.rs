@@ -0,0 +1,527 @@
+use async_trait::async_trait;
+use azure_core::request_options::{IfMatchCondition, Range};
+use azure_storage::prelude::*;
+use azure_storage_blobs::container::operations::list_blobs::BlobItem;
+use azure_storage_blobs::prelude::*;
+use clap::ValueEnum;
+use futures::stream::{self, StreamExt};
+use indicatif::ProgressBar;
+use object_store::aws::AmazonS3Builder;
+use object_store::gcp::GoogleCloudStorageBuilder;
+use object_store::{ObjectStore, path::Path as ObjectPath};
+use time::OffsetDateTime;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Metadata describing a single stored object, common to every backend.
+#[derive(Debug, Clone)]
+pub struct Blob {
+    pub name: String,
+    pub last_updated: OffsetDateTime,
+    pub content_length: u64,
+    /// Content-MD5 recorded by the store, as the raw 16-byte digest.
+    pub content_md5: Option<Vec<u8>>,
+    /// Content-Type recorded by the store, if any.
+    pub content_type: Option<String>,
+    /// ETag recorded by the store, used for `If-None-Match` conditional fetches.
+    pub etag: Option<String>,
+}
+
+/// The outcome of a conditional download.
+pub enum Fetch {
+    /// The store reported the cached copy is still current (HTTP 304).
+    NotModified,
+    /// The blob was new or had changed, and its bytes were re-downloaded.
+    ///
+    /// The metadata gathered while fetching travels with the bytes so the caller
+    /// can verify and cache the blob without issuing another `head`.
+    Modified { bytes: Vec<u8>, meta: Blob },
+}
+
+/// Tunables for a chunked, parallel download.
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadOptions {
+    /// Size of each range request, in bytes.
+    pub chunk_size: u64,
+    /// Number of range requests to keep in flight at once.
+    pub concurrency: usize,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: 8 * 1024 * 1024,
+            concurrency: 8,
+        }
+    }
+}
+
+/// Number of attempts made at a single range request before giving up.
+const RANGE_RETRIES: usize = 3;
+
+/// Split `[0, len)` into contiguous half-open ranges of at most `chunk_size`
+/// bytes. The ranges cover the object exactly once, in order, so reassembling
+/// each chunk at its start offset rebuilds the original bytes.
+fn split_ranges(len: u64, chunk_size: u64) -> Vec<(u64, u64)> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start < len {
+        let end = (start + chunk_size).min(len);
+        ranges.push((start, end));
+        start = end;
+    }
+    ranges
+}
+
+/// The cloud provider a backend talks to.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    Azure,
+    S3,
+    Gcs,
+}
+
+/// An object-safe storage abstraction over the supported providers.
+///
+/// The command handlers only ever see a `Box<dyn StorageBackend>`, so the same
+/// `list`/`open` flow works against Azure, S3 or GCS without change.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// List every object under `prefix`.
+    async fn list(&self, prefix: &str) -> Result<Vec<Blob>>;
+
+    /// Fetch the full contents of `name`.
+    async fn get(&self, name: &str) -> Result<Vec<u8>>;
+
+    /// Fetch only the metadata of `name`.
+    async fn head(&self, name: &str) -> Result<Blob>;
+
+    /// Upload `bytes` to `name`, overwriting any existing object.
+    async fn put(&self, name: &str, bytes: Vec<u8>) -> Result<()>;
+
+    /// Remove the object `name`.
+    async fn delete(&self, name: &str) -> Result<()>;
+
+    /// Download `name`, advancing `progress` by bytes completed.
+    ///
+    /// `meta` carries the object's already-fetched metadata (length in
+    /// particular), so a backend that splits the object into ranges does not
+    /// issue its own `head`. The default implementation fetches the object in
+    /// one shot; backends that support range requests override this to download
+    /// chunks concurrently.
+    async fn download(
+        &self,
+        name: &str,
+        _opts: &DownloadOptions,
+        meta: &Blob,
+        progress: &ProgressBar,
+    ) -> Result<Vec<u8>> {
+        let bytes = self.get(name).await?;
+        progress.set_length(meta.content_length.max(bytes.len() as u64));
+        progress.set_position(bytes.len() as u64);
+        Ok(bytes)
+    }
+
+    /// Download `name` unless it still matches the cached blob.
+    ///
+    /// The default implementation compares the store's current ETag (falling
+    /// back to the last-modified timestamp when no ETag is exposed) against what
+    /// the caller cached using a single `head`, whose result is reused for the
+    /// ensuing [`StorageBackend::download`]. Backends that can ask the service to
+    /// settle the condition — see [`AzureBackend`] — override this to issue a
+    /// real `If-None-Match` GET instead.
+    async fn conditional_download(
+        &self,
+        name: &str,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+        opts: &DownloadOptions,
+        progress: &ProgressBar,
+    ) -> Result<Fetch> {
+        let meta = self.head(name).await?;
+        if etag.is_some() || last_modified.is_some() {
+            let current_modified = meta.last_updated.to_string();
+            let unchanged = match (etag, meta.etag.as_deref()) {
+                (Some(want), Some(have)) => want == have,
+                _ => last_modified == Some(current_modified.as_str()),
+            };
+            if unchanged {
+                return Ok(Fetch::NotModified);
+            }
+        }
+        let bytes = self.download(name, opts, &meta, progress).await?;
+        Ok(Fetch::Modified { bytes, meta })
+    }
+}
+
+/// Azure Blob Storage backend, wrapping the original `ContainerClient` flow.
+pub struct AzureBackend {
+    client: ContainerClient,
+}
+
+impl AzureBackend {
+    pub fn new(storage_account: &str, container: &str, key: Option<String>) -> Result<Self> {
+        let credentials = match key {
+            Some(key) => StorageCredentials::access_key(storage_account.to_owned(), key),
+            None => {
+                let credential = azure_identity::create_credential()?;
+                StorageCredentials::token_credential(credential)
+            }
+        };
+
+        let client =
+            ClientBuilder::new(storage_account, credentials).container_client(container);
+
+        Ok(Self { client })
+    }
+}
+
+fn make_blob(blob_item: BlobItem) -> Option<Blob> {
+    match blob_item {
+        BlobItem::Blob(blob) => Some(Blob {
+            name: blob.name,
+            last_updated: blob.properties.last_modified,
+            content_length: blob.properties.content_length,
+            content_md5: blob
+                .properties
+                .content_md5
+                .map(|md5| md5.as_slice().to_vec()),
+            content_type: Some(blob.properties.content_type),
+            etag: Some(blob.properties.etag.to_string()),
+        }),
+        BlobItem::BlobPrefix(_) => None,
+    }
+}
+
+#[async_trait]
+impl StorageBackend for AzureBackend {
+    async fn list(&self, prefix: &str) -> Result<Vec<Blob>> {
+        let mut list_stream = self.client.list_blobs().prefix(prefix.to_owned()).into_stream();
+        let mut ret: Vec<Blob> = Vec::new();
+
+        while let Some(value) = list_stream.next().await {
+            let response = value?;
+            ret.extend(response.blobs.items.into_iter().filter_map(make_blob));
+        }
+        Ok(ret)
+    }
+
+    async fn get(&self, name: &str) -> Result<Vec<u8>> {
+        let blob_client = self.client.blob_client(name);
+        let mut stream = blob_client.get().into_stream();
+        let mut result: Vec<u8> = vec![];
+
+        while let Some(value) = stream.next().await {
+            let mut body = value?.data;
+            while let Some(value) = body.next().await {
+                result.extend(&value?);
+            }
+        }
+        Ok(result)
+    }
+
+    async fn head(&self, name: &str) -> Result<Blob> {
+        let blob_client = self.client.blob_client(name);
+        let properties = blob_client.get_properties().await?;
+        Ok(Blob {
+            name: name.to_owned(),
+            last_updated: properties.blob.properties.last_modified,
+            content_length: properties.blob.properties.content_length,
+            content_md5: properties
+                .blob
+                .properties
+                .content_md5
+                .map(|md5| md5.as_slice().to_vec()),
+            content_type: Some(properties.blob.properties.content_type),
+            etag: Some(properties.blob.properties.etag.to_string()),
+        })
+    }
+
+    async fn put(&self, name: &str, bytes: Vec<u8>) -> Result<()> {
+        self.client.blob_client(name).put_block_blob(bytes).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        self.client.blob_client(name).delete().await?;
+        Ok(())
+    }
+
+    async fn download(
+        &self,
+        name: &str,
+        opts: &DownloadOptions,
+        meta: &Blob,
+        progress: &ProgressBar,
+    ) -> Result<Vec<u8>> {
+        let len = meta.content_length;
+        progress.set_length(len);
+        progress.set_position(0);
+
+        // Split [0, len) into fixed-size, half-open ranges.
+        let ranges = split_ranges(len, opts.chunk_size);
+
+        let mut buffer = vec![0u8; len as usize];
+
+        // Drain the range requests as they land rather than collecting them all
+        // first, so the bar advances by bytes completed across the transfer
+        // instead of jumping to 100% once everything is already in memory.
+        let mut chunks = stream::iter(ranges)
+            .map(|(start, end)| async move {
+                let bytes = self.get_range(name, start, end).await?;
+                Ok::<(u64, Vec<u8>), Box<dyn std::error::Error>>((start, bytes))
+            })
+            .buffer_unordered(opts.concurrency);
+
+        while let Some(chunk) = chunks.next().await {
+            let (start, bytes) = chunk?;
+            let start = start as usize;
+            buffer[start..start + bytes.len()].copy_from_slice(&bytes);
+            progress.inc(bytes.len() as u64);
+        }
+
+        Ok(buffer)
+    }
+
+    async fn conditional_download(
+        &self,
+        name: &str,
+        etag: Option<&str>,
+        _last_modified: Option<&str>,
+        opts: &DownloadOptions,
+        progress: &ProgressBar,
+    ) -> Result<Fetch> {
+        // Let the service settle the condition: a GET carrying `If-None-Match`
+        // comes back `304 Not Modified` when the cached ETag still holds, so a
+        // cache hit costs one cheap round-trip and never streams the body.
+        if let Some(etag) = etag {
+            if self.unchanged_if_none_match(name, etag).await? {
+                return Ok(Fetch::NotModified);
+            }
+        }
+
+        // Changed (or never cached): a single metadata round-trip feeds the
+        // length, integrity digest, content type and ETag for everything below.
+        let meta = self.head(name).await?;
+        let bytes = self.download(name, opts, &meta, progress).await?;
+        Ok(Fetch::Modified { bytes, meta })
+    }
+}
+
+impl AzureBackend {
+    /// Issue a one-byte conditional GET and report whether the service answered
+    /// `304 Not Modified` for the caller's cached ETag.
+    async fn unchanged_if_none_match(&self, name: &str, etag: &str) -> Result<bool> {
+        let blob_client = self.client.blob_client(name);
+        let response = blob_client
+            .get()
+            .range(Range::new(0, 1))
+            .if_match(IfMatchCondition::NotMatch(etag.to_owned()))
+            .into_stream()
+            .next()
+            .await;
+
+        match response {
+            Some(Ok(_)) => Ok(false),
+            Some(Err(err)) if is_not_modified(&err) => Ok(true),
+            Some(Err(err)) => Err(err.into()),
+            None => Ok(false),
+        }
+    }
+}
+
+/// Whether an Azure error carries an HTTP `304 Not Modified` status.
+fn is_not_modified(err: &azure_core::Error) -> bool {
+    err.as_http_error()
+        .map(|http| http.status() == azure_core::StatusCode::NotModified)
+        .unwrap_or(false)
+}
+
+impl AzureBackend {
+    /// Fetch the half-open byte range `[start, end)`, retrying on transient errors.
+    async fn get_range(&self, name: &str, start: u64, end: u64) -> Result<Vec<u8>> {
+        let blob_client = self.client.blob_client(name);
+        let mut last_err: Option<Box<dyn std::error::Error>> = None;
+
+        for _ in 0..RANGE_RETRIES {
+            let mut stream = blob_client
+                .get()
+                .range(Range::new(start, end))
+                .into_stream();
+            let mut result: Vec<u8> = Vec::with_capacity((end - start) as usize);
+
+            let mut ok = true;
+            while let Some(value) = stream.next().await {
+                match value {
+                    Ok(response) => {
+                        let mut body = response.data;
+                        while let Some(value) = body.next().await {
+                            match value {
+                                Ok(bytes) => result.extend(&bytes),
+                                Err(err) => {
+                                    last_err = Some(err.into());
+                                    ok = false;
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        last_err = Some(err.into());
+                        ok = false;
+                    }
+                }
+                if !ok {
+                    break;
+                }
+            }
+
+            if ok {
+                // Guard the caller's `copy_from_slice`: a proxy or emulator that
+                // ignores the range and returns a full 200 body would otherwise
+                // overflow the destination slice and panic, and a short read
+                // would leave part of it unfilled.
+                let expected = (end - start) as usize;
+                if result.len() != expected {
+                    last_err = Some(
+                        format!(
+                            "range [{}, {}) returned {} bytes, expected {}",
+                            start,
+                            end,
+                            result.len(),
+                            expected
+                        )
+                        .into(),
+                    );
+                    continue;
+                }
+                return Ok(result);
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| "range request failed".into()))
+    }
+}
+
+/// Backend for any provider reachable through the `object_store` crate (S3, GCS).
+///
+/// `object_store`'s metadata exposes the size, last-modified time and ETag but
+/// not the Content-MD5 or Content-Type, so [`Blob::content_md5`] and
+/// [`Blob::content_type`] are always `None` here. In practice that means the
+/// Content-MD5 integrity check and the Content-Type extension fallback are
+/// no-ops on these backends — see the `--no-verify` help.
+struct ObjectStoreBackend {
+    store: Box<dyn ObjectStore>,
+}
+
+#[async_trait]
+impl StorageBackend for ObjectStoreBackend {
+    async fn list(&self, prefix: &str) -> Result<Vec<Blob>> {
+        let prefix = ObjectPath::from(prefix);
+        let mut stream = self.store.list(Some(&prefix));
+        let mut ret: Vec<Blob> = Vec::new();
+
+        while let Some(meta) = stream.next().await {
+            let meta = meta?;
+            ret.push(Blob {
+                name: meta.location.to_string(),
+                last_updated: OffsetDateTime::from_unix_timestamp(meta.last_modified.timestamp())?,
+                content_length: meta.size as u64,
+                // `object_store` surfaces neither digest nor content type.
+                content_md5: None,
+                content_type: None,
+                etag: meta.e_tag.clone(),
+            });
+        }
+        Ok(ret)
+    }
+
+    async fn get(&self, name: &str) -> Result<Vec<u8>> {
+        let bytes = self.store.get(&ObjectPath::from(name)).await?.bytes().await?;
+        Ok(bytes.to_vec())
+    }
+
+    async fn head(&self, name: &str) -> Result<Blob> {
+        let meta = self.store.head(&ObjectPath::from(name)).await?;
+        Ok(Blob {
+            name: meta.location.to_string(),
+            last_updated: OffsetDateTime::from_unix_timestamp(meta.last_modified.timestamp())?,
+            content_length: meta.size as u64,
+            // `object_store` surfaces neither digest nor content type.
+            content_md5: None,
+            content_type: None,
+            etag: meta.e_tag.clone(),
+        })
+    }
+
+    async fn put(&self, name: &str, bytes: Vec<u8>) -> Result<()> {
+        self.store.put(&ObjectPath::from(name), bytes.into()).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        self.store.delete(&ObjectPath::from(name)).await?;
+        Ok(())
+    }
+}
+
+/// Build the backend selected on the command line.
+///
+/// Azure keeps the original credential flow; S3 and GCS pick their credentials
+/// up from the environment the way their respective SDKs expect.
+pub fn build_backend(
+    kind: BackendKind,
+    storage_account: &str,
+    container: &str,
+    key: Option<String>,
+) -> Result<Box<dyn StorageBackend>> {
+    match kind {
+        BackendKind::Azure => Ok(Box::new(AzureBackend::new(storage_account, container, key)?)),
+        BackendKind::S3 => {
+            let store = AmazonS3Builder::from_env().with_bucket_name(container).build()?;
+            Ok(Box::new(ObjectStoreBackend { store: Box::new(store) }))
+        }
+        BackendKind::Gcs => {
+            let store = GoogleCloudStorageBuilder::from_env()
+                .with_bucket_name(container)
+                .build()?;
+            Ok(Box::new(ObjectStoreBackend { store: Box::new(store) }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_evenly_divisible_length() {
+        assert_eq!(split_ranges(12, 4), vec![(0, 4), (4, 8), (8, 12)]);
+    }
+
+    #[test]
+    fn last_range_holds_the_remainder() {
+        assert_eq!(split_ranges(10, 4), vec![(0, 4), (4, 8), (8, 10)]);
+    }
+
+    #[test]
+    fn short_object_is_a_single_range() {
+        assert_eq!(split_ranges(3, 8), vec![(0, 3)]);
+    }
+
+    #[test]
+    fn empty_object_has_no_ranges() {
+        assert!(split_ranges(0, 8).is_empty());
+    }
+
+    #[test]
+    fn ranges_cover_every_byte_exactly_once() {
+        let ranges = split_ranges(37, 8);
+        let mut covered = 0;
+        for (start, end) in ranges {
+            assert_eq!(start, covered);
+            covered = end;
+        }
+        assert_eq!(covered, 37);
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// zstd compression level used for cached blobs.
+const ZSTD_LEVEL: i32 = 3;
+
+/// Blobs below this many bytes are cached verbatim; compression rarely pays off
+/// on small objects and keeps them trivially inspectable on disk.
+pub const COMPRESS_THRESHOLD: usize = 64 * 1024;
+
+/// A self-describing cache payload, mirroring Garage's block store: either the
+/// bytes verbatim or a zstd-compressed frame. A compressed file carries a `.zst`
+/// suffix on its name; re-opens take the file name and the compressed flag from
+/// the [`CacheManifest`] written beside it.
+pub enum DataBlock {
+    Plain(Vec<u8>),
+    Compressed(Vec<u8>),
+}
+
+impl DataBlock {
+    /// Compress `data` when compression is enabled and it clears the threshold,
+    /// otherwise keep it plain.
+    pub fn encode(data: Vec<u8>, compress: bool, threshold: usize) -> Result<Self> {
+        if compress && data.len() >= threshold {
+            let compressed = zstd::encode_all(data.as_slice(), ZSTD_LEVEL)?;
+            Ok(DataBlock::Compressed(compressed))
+        } else {
+            Ok(DataBlock::Plain(data))
+        }
+    }
+
+    /// Whether this block is stored compressed.
+    pub fn is_compressed(&self) -> bool {
+        matches!(self, DataBlock::Compressed(_))
+    }
+
+    /// The bytes as they should be written to the cache file.
+    pub fn stored_bytes(&self) -> &[u8] {
+        match self {
+            DataBlock::Plain(bytes) | DataBlock::Compressed(bytes) => bytes,
+        }
+    }
+
+    /// The original, decompressed contents.
+    pub fn decode(&self) -> Result<Vec<u8>> {
+        match self {
+            DataBlock::Plain(bytes) => Ok(bytes.clone()),
+            DataBlock::Compressed(bytes) => Ok(zstd::decode_all(bytes.as_slice())?),
+        }
+    }
+}
+
+/// Sidecar manifest written next to each cached blob. It records the store's
+/// ETag (or last-modified) so a later `open` can issue a conditional GET and
+/// reuse the cached file untouched when the blob has not changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheManifest {
+    /// ETag of the cached blob, sent back as `If-None-Match`.
+    pub etag: Option<String>,
+    /// Last-modified timestamp, a fallback for stores that expose no ETag.
+    pub last_modified: Option<String>,
+    /// Name of the cache file on disk, relative to the manifest's directory;
+    /// carries the `.zst` suffix when the payload is compressed.
+    pub file_name: String,
+    /// Whether `file_name` holds a zstd frame rather than the raw bytes.
+    pub compressed: bool,
+}
+
+impl CacheManifest {
+    /// The manifest path sitting beside the cache entry for `blob_name`.
+    pub fn path_for(blobs_dir: &Path, blob_name: &str) -> PathBuf {
+        let mut path = blobs_dir.join(blob_name).into_os_string();
+        path.push(".meta.json");
+        PathBuf::from(path)
+    }
+
+    /// Load the manifest recorded for `blob_name`, if a prior run wrote one.
+    pub fn load(blobs_dir: &Path, blob_name: &str) -> Option<Self> {
+        let data = std::fs::read(Self::path_for(blobs_dir, blob_name)).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// Persist this manifest beside the cache entry for `blob_name`.
+    pub fn store(&self, blobs_dir: &Path, blob_name: &str) -> Result<()> {
+        let path = Self::path_for(blobs_dir, blob_name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compressed_block_round_trips() {
+        let data = vec![7u8; COMPRESS_THRESHOLD * 4];
+        let block = DataBlock::encode(data.clone(), true, COMPRESS_THRESHOLD).unwrap();
+        assert!(block.is_compressed());
+        // A redundant payload should actually shrink on disk.
+        assert!(block.stored_bytes().len() < data.len());
+        assert_eq!(block.decode().unwrap(), data);
+    }
+
+    #[test]
+    fn at_threshold_compresses_below_stays_plain() {
+        let at = vec![0u8; COMPRESS_THRESHOLD];
+        assert!(DataBlock::encode(at, true, COMPRESS_THRESHOLD)
+            .unwrap()
+            .is_compressed());
+
+        let below = vec![0u8; COMPRESS_THRESHOLD - 1];
+        assert!(!DataBlock::encode(below, true, COMPRESS_THRESHOLD)
+            .unwrap()
+            .is_compressed());
+    }
+
+    #[test]
+    fn compression_disabled_keeps_bytes_plain() {
+        let data = vec![0u8; COMPRESS_THRESHOLD * 2];
+        let block = DataBlock::encode(data.clone(), false, COMPRESS_THRESHOLD).unwrap();
+        assert!(!block.is_compressed());
+        assert_eq!(block.stored_bytes(), data.as_slice());
+        assert_eq!(block.decode().unwrap(), data);
+    }
+}
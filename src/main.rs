@@ -1,23 +1,18 @@
-use azure_storage::prelude::*;
-use azure_storage_blobs::container::operations::list_blobs::BlobItem;
-use azure_storage_blobs::prelude::*;
+mod backend;
+mod cache;
+
+use backend::{build_backend, BackendKind, Blob, DownloadOptions, Fetch, StorageBackend};
+use cache::{CacheManifest, DataBlock, COMPRESS_THRESHOLD};
 use clap::{Parser, Subcommand};
-use futures::stream::StreamExt;
+use filetime::{set_file_mtime, FileTime};
 use indicatif::{ProgressBar, ProgressStyle};
-use serde::{Deserialize, Serialize};
 use std::env::current_exe;
 use std::fs::{self, File};
 use std::io::prelude::*;
-use std::path::Path;
-use std::str;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use time::OffsetDateTime;
 
-#[derive(Serialize, Deserialize)]
-struct StorageAccountKey {
-    value: String,
-}
-
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct StorageArgs {
@@ -43,11 +38,26 @@ struct StorageArgs {
     )]
     container: String,
 
+    /// Storage backend to talk to
+    #[arg(short('b'), long("backend"), env("STORAGE_BACKEND"), default_value("azure"))]
+    backend: BackendKind,
+
     /// Prefix of the blob
     #[arg(required(true), index(1))]
     prefix: String,
 }
 
+impl StorageArgs {
+    fn backend(&self) -> Result<Box<dyn StorageBackend>> {
+        build_backend(
+            self.backend,
+            &self.storage_account,
+            &self.container,
+            self.storage_account_key.clone(),
+        )
+    }
+}
+
 #[derive(Parser, Debug)]
 struct OpenArgs {
 
@@ -56,8 +66,73 @@ struct OpenArgs {
 
     /// Name of the blob
     #[arg(long, short = 'n')]
-    name: Option<String>
+    name: Option<String>,
+
+    /// Number of range requests to keep in flight when downloading
+    #[arg(long, default_value_t = 8)]
+    concurrency: usize,
+
+    /// Size of each range request, in bytes
+    #[arg(long, default_value_t = 8 * 1024 * 1024)]
+    chunk_size: u64,
+
+    /// Skip the Content-MD5 integrity check on the downloaded blob.
+    /// Only the Azure backend records a Content-MD5; the S3 and GCS backends
+    /// expose none through `object_store`, so the check is already a no-op there
+    /// (as is Content-Type extension sniffing) regardless of this flag.
+    #[arg(long)]
+    no_verify: bool,
+
+    /// Save the blob to this path instead of opening it.
+    /// A directory keeps the blob's own file name.
+    #[arg(long, short = 'd')]
+    download: Option<PathBuf>,
+
+    /// Compress large cached blobs with zstd (the default)
+    #[arg(long, overrides_with = "no_compress")]
+    compress: bool,
+
+    /// Store cached blobs uncompressed
+    #[arg(long)]
+    no_compress: bool,
+
+}
+
+#[derive(Parser, Debug)]
+struct PutArgs {
+
+    #[clap(flatten)]
+    storage: StorageArgs,
+
+    /// Name to store the blob under (joined to the prefix)
+    #[arg(long, short = 'n', required(true))]
+    name: String,
+
+    /// Local file to upload; reads from stdin when omitted
+    #[arg(long, short = 'f')]
+    file: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct DeleteArgs {
+
+    #[clap(flatten)]
+    storage: StorageArgs,
+
+    /// Name of the blob to delete (joined to the prefix)
+    #[arg(long, short = 'n', required(true))]
+    name: String,
+}
+
+#[derive(Parser, Debug)]
+struct SyncArgs {
 
+    #[clap(flatten)]
+    storage: StorageArgs,
+
+    /// Local directory to mirror against the prefix
+    #[arg(required(true), index(2))]
+    dir: PathBuf,
 }
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
@@ -65,7 +140,7 @@ type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct App {
-    
+
     #[command(subcommand)]
     command: Command,
 }
@@ -79,8 +154,18 @@ enum Command {
     List(StorageArgs),
 
     /// Open a blob under the specified prefix.
-    /// Blob updated most recently will be opened if no --name argument is provided. 
-    Open(OpenArgs)
+    /// Blob updated most recently will be opened if no --name argument is provided.
+    Open(OpenArgs),
+
+    /// Upload a local file (or stdin) to the specified prefix.
+    Put(PutArgs),
+
+    /// Delete a blob under the specified prefix.
+    Delete(DeleteArgs),
+
+    /// Mirror a local directory against the specified prefix.
+    /// Changed local files are uploaded and remote-only blobs are downloaded.
+    Sync(SyncArgs)
 }
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -91,10 +176,6 @@ async fn main() -> Result<()> {
     bar.set_style(ProgressStyle::with_template("[{elapsed_precise}] {msg}").unwrap());
     bar.enable_steady_tick(Duration::from_millis(100));
 
-    let credential = azure_identity::create_credential()?;
-
-    let storage_credentials = StorageCredentials::token_credential(credential);
-
     match app.command {
 
         Command::Clean => {
@@ -103,21 +184,17 @@ async fn main() -> Result<()> {
           let _ = clean();
           bar.set_message("Done");
           bar.finish();
-        
+
         },
 
         Command::List(args) => {
 
-          let blob_container_client = ClientBuilder::new(&args.storage_account, storage_credentials)
-            .container_client(&args.container);
+          let backend = args.backend()?;
 
           bar.set_message("Finding blobs.");
 
-          let blobs = 
-              list_blobs(&blob_container_client, args.prefix).await?
-              .into_iter()
-              .filter_map(make_blob);
-  
+          let blobs = backend.list(&args.prefix).await?;
+
           for blob in blobs {
               println!("{} - {}", blob.name, blob.last_updated);
           }
@@ -127,40 +204,88 @@ async fn main() -> Result<()> {
 
         Command::Open(args) => {
 
-          let blob_container_client = ClientBuilder::new(&args.storage.storage_account, storage_credentials)
-            .container_client(&args.storage.container);
+          let backend = args.storage.backend()?;
+          let opts = DownloadOptions {
+              chunk_size: args.chunk_size,
+              concurrency: args.concurrency,
+          };
 
           if let Some(name) = args.name {
 
             let blob_name = format! ("{}/{}", args.storage.prefix, name);
             bar.set_message(format!("Downloading {}", &blob_name));
-            let _ = process_blob(&blob_container_client, &blob_name).await;
+            let _ = process_blob(backend.as_ref(), &blob_name, &opts, args.no_verify, args.download.as_deref(), args.compress || !args.no_compress, &bar).await;
 
-          } 
+          }
           else {
 
             bar.set_message("Finding the latest blob.");
-            let latest_blob = get_latest_blob(&blob_container_client, &args.storage.prefix).await;
-    
+            let latest_blob = get_latest_blob(backend.as_ref(), &args.storage.prefix).await;
+
             if let Some(blob) = latest_blob {
+                // We already know the latest blob's ETag from the listing, so a
+                // cache hit needs no further request at all — reuse it directly.
+                if args.download.is_none() {
+                    if let Some(hit) = cached_hit(&blob) {
+                        bar.set_message(format!("Reusing cached {}", &blob.name));
+                        let _ = open_cached(&hit.0, hit.1);
+                        bar.finish();
+                        return Ok(());
+                    }
+                }
                 bar.set_message(format!("Downloading {}", &blob.name));
-                let _ = process_blob(&blob_container_client, &blob.name).await;
+                let _ = process_blob(backend.as_ref(), &blob.name, &opts, args.no_verify, args.download.as_deref(), args.compress || !args.no_compress, &bar).await;
             }
           }
           bar.finish();
 
         }
+
+        Command::Put(args) => {
+
+          let backend = args.storage.backend()?;
+          let blob_name = format!("{}/{}", args.storage.prefix, args.name);
+
+          let bytes = match &args.file {
+              Some(path) => fs::read(path)?,
+              None => {
+                  let mut buf = Vec::new();
+                  std::io::stdin().read_to_end(&mut buf)?;
+                  buf
+              }
+          };
+
+          bar.set_message(format!("Uploading {}", &blob_name));
+          backend.put(&blob_name, bytes).await?;
+          bar.finish();
+
+        }
+
+        Command::Delete(args) => {
+
+          let backend = args.storage.backend()?;
+          let blob_name = format!("{}/{}", args.storage.prefix, args.name);
+
+          bar.set_message(format!("Deleting {}", &blob_name));
+          backend.delete(&blob_name).await?;
+          bar.finish();
+
+        }
+
+        Command::Sync(args) => {
+
+          let backend = args.storage.backend()?;
+
+          bar.set_message("Synchronising directory.");
+          sync(backend.as_ref(), &args.storage.prefix, &args.dir).await?;
+          bar.finish();
+
+        }
     }
 
     Ok(())
 }
 
-#[derive(Debug, Clone)]
-struct Blob {
-    name: String,
-    last_updated: OffsetDateTime,
-}
-
 fn clean() -> Result<()> {
   let current_dir = current_exe()?;
 
@@ -194,53 +319,90 @@ fn clean() -> Result<()> {
   Ok(())
 }
 
-fn make_blob(blob_item: BlobItem) -> Option<Blob> {
-    match blob_item {
-        BlobItem::Blob(blob) => Some(Blob {
-            name: blob.name,
-            last_updated: blob.properties.last_modified,
-        }),
-        BlobItem::BlobPrefix(_) => None,
-    }
+/// Render bytes as a lowercase hex string, for integrity error messages.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
 }
 
-async fn list_blobs(
-    blob_container_client: &ContainerClient,
-    prefix: String,
-) -> Result<Vec<BlobItem>> {
-    let mut list_stream = blob_container_client
-        .list_blobs()
-        .prefix(prefix)
-        .into_stream();
-    let mut ret: Vec<BlobItem> = Vec::new();
-
-    while let Some(value) = list_stream.next().await {
-        let _blobs = value.map(|list_response| ret.extend_from_slice(&list_response.blobs.items));
+/// Collect every file under `dir`, returned as paths relative to `base`.
+fn walk_dir(base: &Path, dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk_dir(base, &path, out)?;
+        } else if let Ok(rel) = path.strip_prefix(base) {
+            out.push(rel.to_path_buf());
+        }
     }
-    Ok(ret)
+    Ok(())
 }
 
-async fn get_blob(blob_container_client: &ContainerClient, blob_name: &str) -> Result<Vec<u8>> {
-    let blob_client = blob_container_client.blob_client(blob_name);
-    let mut stream = blob_client.get().into_stream();
-    let mut result: Vec<u8> = vec![];
+/// Mirror `dir` against `prefix`: upload files that are new or newer locally,
+/// and download blobs that only exist remotely. Comparison is by last-modified.
+async fn sync(backend: &dyn StorageBackend, prefix: &str, dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let remote: Vec<Blob> = backend.list(prefix).await?;
+
+    let mut local_files = Vec::new();
+    walk_dir(dir, dir, &mut local_files)?;
+
+    // Upload local files that are new or newer than their remote counterpart.
+    for rel in &local_files {
+        let blob_name = format!("{}/{}", prefix, rel.to_string_lossy());
+        let local_modified: OffsetDateTime = fs::metadata(dir.join(rel))?.modified()?.into();
 
-    while let Some(value) = stream.next().await {
-        let mut body = value?.data;
-        while let Some(value) = body.next().await {
-            let value = value?;
-            result.extend(&value);
+        let needs_upload = match remote.iter().find(|blob| blob.name == blob_name) {
+            Some(blob) => local_modified > blob.last_updated,
+            None => true,
+        };
+
+        if needs_upload {
+            println!("upload {}", blob_name);
+            backend.put(&blob_name, fs::read(dir.join(rel))?).await?;
+        }
+    }
+
+    // Download blobs that have no local counterpart.
+    let local_names: std::collections::HashSet<String> = local_files
+        .iter()
+        .map(|rel| format!("{}/{}", prefix, rel.to_string_lossy()))
+        .collect();
+
+    for blob in &remote {
+        if local_names.contains(&blob.name) {
+            continue;
+        }
+        let rel = blob
+            .name
+            .strip_prefix(&format!("{}/", prefix))
+            .unwrap_or(&blob.name);
+        let dest = dir.join(rel);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
         }
+        println!("download {}", blob.name);
+        let bytes = backend.get(&blob.name).await?;
+        File::create(&dest)?.write_all(&bytes)?;
+
+        // Stamp the mirrored file with the blob's server timestamp. Leaving the
+        // fresh download mtime at "now" would make it look newer than the
+        // remote on the next run and re-upload byte-identical content forever.
+        let mtime = FileTime::from_unix_time(
+            blob.last_updated.unix_timestamp(),
+            blob.last_updated.nanosecond(),
+        );
+        set_file_mtime(&dest, mtime)?;
     }
-    Ok(result)
+
+    Ok(())
 }
 
-async fn get_latest_blob(blob_container_client: &ContainerClient, prefix: &str) -> Option<Blob> {
-    list_blobs(blob_container_client, prefix.into())
+async fn get_latest_blob(backend: &dyn StorageBackend, prefix: &str) -> Option<Blob> {
+    backend
+        .list(prefix)
         .await
-        .map(|items| {
-            let mut blobs: Vec<Blob> = items.into_iter().filter_map(make_blob).collect();
-
+        .map(|mut blobs| {
             blobs.sort_by(|a, b| b.last_updated.cmp(&a.last_updated));
             blobs.first().cloned()
         })
@@ -248,53 +410,308 @@ async fn get_latest_blob(blob_container_client: &ContainerClient, prefix: &str)
         .flatten()
 }
 
-async fn process_blob(blob_container_client: &ContainerClient, blob_name: &str) -> Result<()> {
+async fn process_blob(
+    backend: &dyn StorageBackend,
+    blob_name: &str,
+    opts: &DownloadOptions,
+    no_verify: bool,
+    download: Option<&Path>,
+    compress: bool,
+    bar: &ProgressBar,
+) -> Result<()> {
     let file_name = blob_name
         .split("/")
         .last()
         .map(|x| x.trim())
         .unwrap_or("unknown");
 
-    // Retrieve the blob
-    let blob_content = get_blob(blob_container_client, blob_name)
+    bar.set_style(
+        ProgressStyle::with_template("[{elapsed_precise}] {bar:40} {bytes}/{total_bytes} {msg}")
+            .unwrap(),
+    );
+
+    // An explicit --download path saves the raw bytes verbatim, uncompressed,
+    // so binary blobs survive intact where the user asked for them. It never
+    // consults the cache: the user asked for a fresh copy at a chosen path.
+    if let Some(path) = download {
+        // A single metadata round-trip drives the length, integrity check and
+        // extension fallback; nothing below issues a second `head`.
+        let meta = backend.head(blob_name).await.ok();
+        let blob_content = match &meta {
+            Some(meta) => backend.download(blob_name, opts, meta, bar).await,
+            None => backend.get(blob_name).await,
+        }
+        .map_err(|err| format!("Error retrieving blob: {}", err))?;
+        verify_md5(blob_name, &blob_content, meta.as_ref(), no_verify)?;
+
+        let file_name = with_extension(file_name, meta.as_ref());
+        let dest = if path.is_dir() { path.join(&file_name) } else { path.to_path_buf() };
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        File::create(&dest)?.write_all(&blob_content)?;
+        println!("Saved {} to {}", blob_name, dest.display());
+        return Ok(());
+    }
+
+    // Cache mode: reuse the copy already under the 'blobs' directory when the
+    // store still holds the same blob. The manifest carries the ETag recorded
+    // on the prior download, which drives a conditional GET (`If-None-Match`).
+    let blobs_dir = blobs_dir()?;
+    let cache_dir = cache_dir_for(&blobs_dir, blob_name)?;
+    let manifest = CacheManifest::load(&blobs_dir, blob_name);
+    let (etag, last_modified) = match &manifest {
+        Some(m) => (m.etag.clone(), m.last_modified.clone()),
+        None => (None, None),
+    };
+
+    let fetch = backend
+        .conditional_download(blob_name, etag.as_deref(), last_modified.as_deref(), opts, bar)
         .await
         .map_err(|err| format!("Error retrieving blob: {}", err))?;
 
-    // Convert the blob content to a string (handling UTF-8 errors)
-    let blob_content_str = std::str::from_utf8(&blob_content)
-        .map_err(|err| format!("Invalid UTF-8 sequence: {}", err))?;
+    let (blob_content, meta) = match fetch {
+        Fetch::NotModified => {
+            if let Some(manifest) = &manifest {
+                let cache_path = cache_dir.join(&manifest.file_name);
+                if cache_path.exists() {
+                    bar.set_message(format!("Reusing cached {}", blob_name));
+                    return open_cached(&cache_path, manifest.compressed);
+                }
+            }
+            // The store says nothing changed but the cache file is gone; the
+            // manifest is stale, so fall back to a full download.
+            let meta = backend.head(blob_name).await.ok();
+            let bytes = match &meta {
+                Some(meta) => backend.download(blob_name, opts, meta, bar).await,
+                None => backend.get(blob_name).await,
+            }
+            .map_err(|err| format!("Error retrieving blob: {}", err))?;
+            (bytes, meta)
+        }
+        // The conditional download already gathered the metadata that drives the
+        // integrity check, the extension fallback and the ETag we record.
+        Fetch::Modified { bytes, meta } => (bytes, Some(meta)),
+    };
+
+    // Verify the download against the Content-MD5 the store holds, so a
+    // truncated or corrupted blob never lands on disk.
+    verify_md5(blob_name, &blob_content, meta.as_ref(), no_verify)?;
+
+    // Give extension-less blobs a sensible suffix based on their Content-Type.
+    let file_name = with_extension(file_name, meta.as_ref());
+
+    // Cache under the 'blobs' directory, transparently zstd-compressing large
+    // objects. A `.zst` suffix keeps the cache self-describing.
+    let file_path = cache_dir.join(&file_name);
+    let block = DataBlock::encode(blob_content, compress, COMPRESS_THRESHOLD)?;
+    let cache_path = if block.is_compressed() {
+        let mut name = file_path.clone().into_os_string();
+        name.push(".zst");
+        PathBuf::from(name)
+    } else {
+        file_path.clone()
+    };
+
+    if let Some(parent) = cache_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    File::create(&cache_path)?.write_all(block.stored_bytes())?;
+
+    // Record the ETag / last-modified beside the file so a later open can reuse
+    // it without re-streaming the blob.
+    let stored_name = cache_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(&file_name)
+        .to_owned();
+    CacheManifest {
+        etag: meta.as_ref().and_then(|blob| blob.etag.clone()),
+        last_modified: meta.as_ref().map(|blob| blob.last_updated.to_string()),
+        file_name: stored_name,
+        compressed: block.is_compressed(),
+    }
+    .store(&blobs_dir, blob_name)?;
+
+    open_cached(&cache_path, block.is_compressed())
+}
+
+/// Return the cached file for `blob` when a manifest records the same ETag (or,
+/// lacking one, the same last-modified) and the file is still on disk. Lets the
+/// latest-blob flow skip the download entirely when nothing has changed.
+fn cached_hit(blob: &Blob) -> Option<(PathBuf, bool)> {
+    let blobs_dir = blobs_dir().ok()?;
+    let manifest = CacheManifest::load(&blobs_dir, &blob.name)?;
+
+    let unchanged = match (&manifest.etag, &blob.etag) {
+        (Some(have), Some(want)) => have == want,
+        _ => manifest.last_modified.as_deref() == Some(blob.last_updated.to_string().as_str()),
+    };
+    if !unchanged {
+        return None;
+    }
 
-    let current_dir = current_exe()?;
+    let cache_path = cache_dir_for(&blobs_dir, &blob.name).ok()?.join(&manifest.file_name);
+    cache_path.exists().then_some((cache_path, manifest.compressed))
+}
 
-    let dir = current_dir
+/// The 'blobs' cache directory, next to the running executable.
+fn blobs_dir() -> Result<PathBuf> {
+    let exe = current_exe()?;
+    Ok(exe
         .parent()
-        .ok_or("Could not find parent directory")?;
+        .ok_or("Could not find parent directory")?
+        .join("blobs"))
+}
 
-    let file_path = dir
-        .join("blobs")
+/// The directory a given blob's cache entry and manifest live in.
+fn cache_dir_for(blobs_dir: &Path, blob_name: &str) -> Result<PathBuf> {
+    Ok(blobs_dir
         .join(blob_name)
         .parent()
         .ok_or("Could not find parent directory")?
-        .join(file_name);
+        .to_path_buf())
+}
 
-    let file_dir = file_path
-        .parent()
-        .ok_or("Could not find parent directory")?;
-    std::fs::create_dir_all(file_dir)?;
+/// Verify `content` against the store's Content-MD5, unless verification is off.
+fn verify_md5(blob_name: &str, content: &[u8], meta: Option<&Blob>, no_verify: bool) -> Result<()> {
+    if no_verify {
+        return Ok(());
+    }
+    if let Some(expected) = meta.and_then(|blob| blob.content_md5.as_ref()) {
+        let digest = md5::compute(content);
+        if digest.0.as_slice() != expected.as_slice() {
+            return Err(format!(
+                "Content-MD5 mismatch for {}: expected {}, computed {}",
+                blob_name,
+                hex(expected),
+                hex(&digest.0)
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
 
-    let mut file = File::create(file_path.clone())?;
+/// Append a Content-Type-derived extension to `file_name` when it lacks one.
+fn with_extension(file_name: &str, meta: Option<&Blob>) -> String {
+    if Path::new(file_name).extension().is_none() {
+        if let Some(ext) = meta
+            .and_then(|blob| blob.content_type.as_deref())
+            .and_then(ext_for_content_type)
+        {
+            return format!("{}.{}", file_name, ext);
+        }
+    }
+    file_name.to_owned()
+}
 
-    let open_path = file_path.as_path();
+/// Open a cache file, decompressing a zstd frame into a temp file first so
+/// `opener` always sees the real, uncompressed content.
+fn open_cached(cache_path: &Path, compressed: bool) -> Result<()> {
+    let open_path = if compressed {
+        let block = DataBlock::Compressed(fs::read(cache_path)?);
+        let name = cache_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.strip_suffix(".zst").unwrap_or(name))
+            .unwrap_or("blob");
+        let tmp = std::env::temp_dir().join(name);
+        File::create(&tmp)?.write_all(&block.decode()?)?;
+        tmp
+    } else {
+        cache_path.to_path_buf()
+    };
+
+    if let Err(err) = opener::open(&open_path).map_err(|err| format!("Error opening file: {}", err))
+    {
+        println!("Error opening file: {}", err);
+    }
 
-    file.write_all(blob_content_str.as_bytes())?;
+    Ok(())
+}
 
-    let open_result =
-        opener::open(Path::new(open_path)).map_err(|err| format!("Error opening file: {}", err));
+/// Map a Content-Type to a file extension, for blobs whose name lacks one.
+fn ext_for_content_type(content_type: &str) -> Option<&'static str> {
+    let base = content_type.split(';').next().unwrap_or("").trim();
+    let ext = match base {
+        "text/plain" => "txt",
+        "text/csv" => "csv",
+        "text/html" => "html",
+        "application/json" => "json",
+        "application/xml" | "text/xml" => "xml",
+        "application/gzip" => "gz",
+        "application/zip" => "zip",
+        "application/pdf" => "pdf",
+        "application/octet-stream" => "bin",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        _ => return None,
+    };
+    Some(ext)
+}
 
-    match open_result {
-        Ok(_) => (),
-        Err(err) => println!("Error opening file: {}", err),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blob(content_md5: Option<Vec<u8>>, content_type: Option<&str>) -> Blob {
+        Blob {
+            name: "blob".to_owned(),
+            last_updated: OffsetDateTime::UNIX_EPOCH,
+            content_length: 0,
+            content_md5,
+            content_type: content_type.map(str::to_owned),
+            etag: None,
+        }
     }
 
-    Ok(())
+    #[test]
+    fn verify_md5_accepts_matching_digest() {
+        let content = b"some bytes";
+        let meta = blob(Some(md5::compute(content).0.to_vec()), None);
+        assert!(verify_md5("blob", content, Some(&meta), false).is_ok());
+    }
+
+    #[test]
+    fn verify_md5_rejects_mismatched_digest() {
+        let meta = blob(Some(md5::compute(b"other").0.to_vec()), None);
+        assert!(verify_md5("blob", b"some bytes", Some(&meta), false).is_err());
+    }
+
+    #[test]
+    fn verify_md5_skipped_when_disabled() {
+        let meta = blob(Some(md5::compute(b"other").0.to_vec()), None);
+        assert!(verify_md5("blob", b"some bytes", Some(&meta), true).is_ok());
+    }
+
+    #[test]
+    fn verify_md5_passes_when_store_records_no_digest() {
+        assert!(verify_md5("blob", b"anything", Some(&blob(None, None)), false).is_ok());
+        assert!(verify_md5("blob", b"anything", None, false).is_ok());
+    }
+
+    #[test]
+    fn extension_inferred_for_extensionless_name() {
+        let meta = blob(None, Some("application/json"));
+        assert_eq!(with_extension("report", Some(&meta)), "report.json");
+    }
+
+    #[test]
+    fn existing_extension_is_left_alone() {
+        let meta = blob(None, Some("application/json"));
+        assert_eq!(with_extension("report.csv", Some(&meta)), "report.csv");
+    }
+
+    #[test]
+    fn unknown_content_type_leaves_name_unchanged() {
+        let meta = blob(None, Some("application/x-made-up"));
+        assert_eq!(with_extension("report", Some(&meta)), "report");
+    }
+
+    #[test]
+    fn content_type_parameters_are_ignored() {
+        assert_eq!(ext_for_content_type("text/plain; charset=utf-8"), Some("txt"));
+    }
 }